@@ -0,0 +1,176 @@
+/// Maximum number of distinct configuration keys.
+const MAX_ENTRIES: usize = 8;
+/// Maximum length of a configuration key, in bytes.
+const MAX_KEY_LEN: usize = 16;
+/// Maximum length of a configuration value, in bytes.
+const MAX_VALUE_LEN: usize = 64;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Entry {
+    used: bool,
+    key_len: u8,
+    key: [u8; MAX_KEY_LEN],
+    value_len: u8,
+    value: [u8; MAX_VALUE_LEN],
+}
+
+impl Entry {
+    const EMPTY: Entry = Entry {
+        used: false,
+        key_len: 0,
+        key: [0u8; MAX_KEY_LEN],
+        value_len: 0,
+        value: [0u8; MAX_VALUE_LEN],
+    };
+
+    fn key(&self) -> &[u8] {
+        &self.key[..self.key_len as usize]
+    }
+}
+
+/// Backing NVM storage for [`Config`], one app-wide table of key/value entries.
+#[repr(C)]
+struct Store {
+    entries: [Entry; MAX_ENTRIES],
+}
+
+#[link_section = ".nvm_data"]
+#[no_mangle]
+static mut N_CONFIG: Store = Store {
+    entries: [Entry::EMPTY; MAX_ENTRIES],
+};
+
+/// Borrows the backing NVM store.
+///
+/// # Safety
+///
+/// The SE runs single-threaded with no interrupts re-entering app code, so `Config`'s methods
+/// never alias this mutable reference.
+unsafe fn store() -> &'static mut Store {
+    &mut *core::ptr::addr_of_mut!(N_CONFIG)
+}
+
+/// Writes `entry` into the NVM slot backing `*dst`, going through the syscall that makes the
+/// write atomic and durable across a reset.
+fn nvm_write_entry(dst: &mut Entry, entry: &Entry) {
+    unsafe {
+        ledger_secure_sdk_sys::nvm_write(
+            dst as *mut Entry as *mut core::ffi::c_void,
+            entry as *const Entry as *mut core::ffi::c_void,
+            core::mem::size_of::<Entry>() as u32,
+        );
+    }
+}
+
+/// An owned copy of a value read back from [`Config`].
+///
+/// [`Config::get`] hands back a copy rather than a reference into the NVM store: a `&'static`
+/// slice pointing at `N_CONFIG` would stay borrowable across a later [`Config::set`]/
+/// [`Config::erase`] call, which takes its own `&mut` into that same static to write through it,
+/// an aliasing violation the borrow checker can't see through `unsafe fn store()`.
+#[derive(Clone, Copy)]
+pub struct ConfigValue {
+    len: u8,
+    bytes: [u8; MAX_VALUE_LEN],
+}
+
+impl ConfigValue {
+    fn from_entry(entry: &Entry) -> Self {
+        ConfigValue {
+            len: entry.value_len,
+            bytes: entry.value,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl core::ops::Deref for ConfigValue {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+/// Small persistent key/value store for app settings (selected network, blind-signing toggle,
+/// display unit, ...), backed by the device's NVM.
+///
+/// Keys are short strings, values are opaque bytes up to [`MAX_VALUE_LEN`] long; there is no
+/// wear levelling beyond what the underlying `nvm_write` syscall already provides, which is
+/// adequate for settings that change rarely compared to e.g. a PIN counter.
+pub struct Config;
+
+impl Config {
+    /// Returns a copy of the value stored under `key`, if any.
+    pub fn get(key: &str) -> Option<ConfigValue> {
+        let key = key.as_bytes();
+        unsafe {
+            store()
+                .entries
+                .iter()
+                .find(|entry| entry.used && entry.key() == key)
+                .map(ConfigValue::from_entry)
+        }
+    }
+
+    /// Sets `key` to `value`, overwriting any previous value. Returns `false` without writing
+    /// anything if `key`/`value` don't fit, or if the table is full and `key` is new.
+    pub fn set(key: &str, value: &[u8]) -> bool {
+        let key_bytes = key.as_bytes();
+        if key_bytes.len() > MAX_KEY_LEN || value.len() > MAX_VALUE_LEN {
+            return false;
+        }
+
+        let store = unsafe { store() };
+        let index = store
+            .entries
+            .iter()
+            .position(|entry| entry.used && entry.key() == key_bytes)
+            .or_else(|| store.entries.iter().position(|entry| !entry.used));
+        let Some(index) = index else {
+            return false;
+        };
+        let slot = &mut store.entries[index];
+
+        let mut updated = Entry::EMPTY;
+        updated.used = true;
+        updated.key_len = key_bytes.len() as u8;
+        updated.key[..key_bytes.len()].copy_from_slice(key_bytes);
+        updated.value_len = value.len() as u8;
+        updated.value[..value.len()].copy_from_slice(value);
+        nvm_write_entry(slot, &updated);
+        true
+    }
+
+    /// Removes `key`, if present. Returns whether a value was actually erased.
+    pub fn erase(key: &str) -> bool {
+        let key_bytes = key.as_bytes();
+        let slot = unsafe {
+            store()
+                .entries
+                .iter_mut()
+                .find(|entry| entry.used && entry.key() == key_bytes)
+        };
+        match slot {
+            Some(slot) => {
+                nvm_write_entry(slot, &Entry::EMPTY);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Writes `value` for every `(key, value)` in `defaults` that isn't already set, so an app
+    /// can provision its compiled-in defaults on first run without clobbering a user's settings.
+    pub fn provision_defaults(defaults: &[(&str, &[u8])]) {
+        for (key, value) in defaults {
+            if Config::get(key).is_none() {
+                Config::set(key, value);
+            }
+        }
+    }
+}
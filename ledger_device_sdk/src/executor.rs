@@ -0,0 +1,43 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+/// Build a [`Waker`] that does nothing when woken.
+///
+/// There is no interrupt or SEPH callback anywhere in this SDK that calls [`Waker::wake`]: every
+/// future reports readiness by being polled again, not by waking itself up asynchronously, so the
+/// waker [`block_on`] hands out is inert by construction.
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        raw_waker()
+    }
+    fn no_op(_: *const ()) {}
+
+    fn raw_waker() -> RawWaker {
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+
+    unsafe { Waker::from_raw(raw_waker()) }
+}
+
+/// Minimal single-task, no-heap executor: poll `fut` until it resolves.
+///
+/// This is a cooperative *spin* executor, not a suspending one: there is no task spawning, no
+/// ready-queue, and no interrupt wired to the `Waker` — between polls nothing actually sleeps, a
+/// pending future is just polled again right away. It exists so app code can write `async fn`
+/// state machines around [`crate::io::Comm::next_event_async`] (and compose them with
+/// hand-written `select!`-style combinators) instead of hand-rolling a loop around
+/// [`crate::io::Comm::next_event`]; `next_event` itself is implemented as `block_on` over the
+/// async variant, so it costs no more than the busy-loop it replaces.
+pub fn block_on<F: Future>(mut fut: F) -> F::Output {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    // SAFETY: `fut` is shadowed by the `Pin` below and never moved again.
+    let mut fut = unsafe { Pin::new_unchecked(&mut fut) };
+    loop {
+        if let Poll::Ready(value) = fut.as_mut().poll(&mut cx) {
+            return value;
+        }
+    }
+}
@@ -4,13 +4,20 @@ use ledger_secure_sdk_sys::buttons::{get_button_event, ButtonEvent, ButtonsState
 use ledger_secure_sdk_sys::seph as sys_seph;
 use ledger_secure_sdk_sys::*;
 
-#[cfg(feature = "ccid")]
-use crate::ccid;
+use crate::config::Config;
+use crate::executor;
 use crate::seph;
+use crate::timer;
+pub use crate::timer::TimerHandle;
+use crate::transport;
+use crate::transport::Transport;
 use core::convert::{Infallible, TryFrom};
+use core::future::Future;
 use core::ops::{Index, IndexMut};
+use core::pin::Pin;
+use core::task::{Context, Poll};
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 #[repr(u16)]
 pub enum StatusWords {
     Ok = 0x9000,
@@ -20,6 +27,7 @@ pub enum StatusWords {
     BadP1P2 = 0x6e02,
     BadLen = 0x6e03,
     UserCancelled = 0x6e04,
+    BadChaining = 0x6e05,
     Unknown = 0x6d00,
     Panic = 0xe000,
 }
@@ -92,11 +100,13 @@ pub enum Event<T> {
     Button(ButtonEvent),
     /// Ticker
     Ticker,
+    /// A timer armed with [`Comm::set_timeout`] or [`Comm::set_periodic_timeout`] expired
+    Timeout(TimerHandle),
 }
 
 /// Manages the communication of the device: receives events such as button presses, incoming
 /// APDU requests, and provides methods to build and transmit APDU responses.
-pub struct Comm {
+pub struct Comm<'a> {
     pub apdu_buffer: [u8; 260],
     pub rx: usize,
     pub tx: usize,
@@ -106,14 +116,88 @@ pub struct Comm {
     /// with wrong CLA byte is received. If set to [`None`], all CLA are accepted.
     /// Can be set using [`Comm::set_expected_cla`] method.
     pub expected_cla: Option<u8>,
+    /// Backing store for ISO 7816-4 command chaining, set with [`Comm::with_chaining_buffer`].
+    /// While set, every block's data field is accumulated here instead of being surfaced
+    /// straight away, so a reassembled command can be larger than [`Comm::apdu_buffer`].
+    chaining_buffer: Option<&'a mut [u8]>,
+    /// Number of bytes of `chaining_buffer` filled so far by the in-progress chain.
+    chaining_len: usize,
+    /// CLA (with the chaining bit masked off), INS, P1 and P2 of the chain in progress, used to
+    /// detect a block that doesn't belong to the same command.
+    chaining_header: Option<[u8; 4]>,
+    /// Response staged with [`Comm::set_response`], still awaiting one or more GET RESPONSE
+    /// APDUs to be fully drained.
+    response_buffer: Option<&'a [u8]>,
+    /// Number of bytes of `response_buffer` already sent.
+    response_offset: usize,
+    /// Maximum number of response bytes sent per frame while draining `response_buffer`. Tune
+    /// this down for transports with a smaller MTU than the default APDU data size.
+    pub max_chunk_size: usize,
+    /// `apdu_media` tag served by `custom_transport`, set together with it by
+    /// [`Comm::register_transport`].
+    custom_transport_media: Option<u8>,
+    /// Application-provided [`Transport`] for an `apdu_media` value none of the built-in media
+    /// (USB HID, raw SEPH, CCID, BLE) handle.
+    custom_transport: Option<&'a mut dyn Transport>,
+    /// Ticker-driven timers armed with [`Comm::set_timeout`]/[`Comm::set_periodic_timeout`].
+    timers: timer::TimerQueue,
 }
 
-impl Default for Comm {
+impl<'a> Default for Comm<'a> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Future returned by [`Comm::next_event_async`].
+///
+/// This is a spin-polled future, not a suspending one: there is no interrupt wired to the
+/// `Waker`, so each poll drains at most one pending SEPH message and decodes it, and if no full
+/// [`Event`] is ready yet it re-wakes itself immediately and returns [`Poll::Pending`] — the
+/// executor (e.g. [`executor::block_on`]) ends up polling it again right away, at the same cost
+/// as the busy-loop this replaces.
+pub struct EventFuture<'c, 'a, T> {
+    comm: &'c mut Comm<'a>,
+    // `fn() -> T` rather than `T` so `EventFuture` stays `Unpin` regardless of `T`: only `comm`
+    // is ever referenced by a pinned pointer, and it's a plain `&mut` reference.
+    _marker: core::marker::PhantomData<fn() -> T>,
+}
+
+impl<'c, 'a, T> Future for EventFuture<'c, 'a, T>
+where
+    T: TryFrom<ApduHeader>,
+    Reply: From<<T as TryFrom<ApduHeader>>::Error>,
+{
+    type Output = Event<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        // Drain any timer that already fired before waiting on the next SEPH message, so
+        // several timers expiring on the same tick are all reported.
+        if let Some(handle) = this.comm.timers.pop_pending() {
+            return Poll::Ready(Event::Timeout(handle));
+        }
+
+        if !sys_seph::is_status_sent() {
+            sys_seph::send_general_status();
+        }
+        let mut spi_buffer = [0u8; 128];
+        sys_seph::seph_recv(&mut spi_buffer, 0);
+
+        match this.comm.decode_event(&mut spi_buffer) {
+            Some(event) => Poll::Ready(event),
+            None => {
+                // Nothing decoded yet. There is no IRQ to wake us when the next SEPH message
+                // arrives, so immediately ask to be polled again: this is a spin loop, not a
+                // suspend.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 #[repr(C)]
 pub struct ApduHeader {
@@ -127,7 +211,7 @@ pub struct ApduHeader {
     pub p2: u8,
 }
 
-impl Comm {
+impl<'a> Comm<'a> {
     /// Creates a new [`Comm`] instance, which accepts any CLA APDU by default.
     pub const fn new() -> Self {
         Self {
@@ -136,6 +220,15 @@ impl Comm {
             tx: 0,
             buttons: ButtonsState::new(),
             expected_cla: None,
+            chaining_buffer: None,
+            chaining_len: 0,
+            chaining_header: None,
+            response_buffer: None,
+            response_offset: 0,
+            max_chunk_size: 255,
+            custom_transport_media: None,
+            custom_transport: None,
+            timers: timer::TimerQueue::new(),
         }
     }
 
@@ -158,10 +251,159 @@ impl Comm {
         self
     }
 
+    /// Backs ISO 7816-4 command chaining with `buffer`: when an incoming APDU's CLA has the
+    /// chaining bit set (`0x10`), its data field is appended to `buffer` instead of being
+    /// surfaced as an [`Event::Command`], and the reassembled command is only returned once a
+    /// block arrives with the chaining bit cleared. See [`Comm::get_chained_data`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut chaining_buffer = [0u8; 4096];
+    /// let mut comm = Comm::new().with_chaining_buffer(&mut chaining_buffer);
+    /// ```
+    pub fn with_chaining_buffer(mut self, buffer: &'a mut [u8]) -> Self {
+        self.chaining_buffer = Some(buffer);
+        self.chaining_len = 0;
+        self.chaining_header = None;
+        self
+    }
+
+    /// Returns the data reassembled so far from an ISO 7816-4 chained command, or an error if no
+    /// [`Comm::with_chaining_buffer`] was configured.
+    pub fn get_chained_data(&self) -> Result<&[u8], StatusWords> {
+        match &self.chaining_buffer {
+            Some(buffer) => Ok(&buffer[..self.chaining_len]),
+            None => Err(StatusWords::Unknown),
+        }
+    }
+
+    /// Accumulates one block's `data` field into the in-progress chain, keyed by `header` (CLA
+    /// bit `0x10` still set if this block isn't the last). Returns `Ok(true)` if more blocks are
+    /// expected, `Ok(false)` once this was the chain's final block and [`Comm::get_chained_data`]
+    /// holds the full command, or `Err` if this block doesn't belong to the in-progress chain or
+    /// would overflow [`Comm::with_chaining_buffer`]'s backing store.
+    fn accumulate_chained_block(
+        &mut self,
+        header: ApduHeader,
+        data: &[u8],
+    ) -> Result<bool, StatusWords> {
+        let chaining_bit_set = header.cla & 0x10 != 0;
+        let chain_key = [header.cla & !0x10, header.ins, header.p1, header.p2];
+
+        match self.chaining_header {
+            Some(in_progress) if in_progress != chain_key => {
+                self.chaining_header = None;
+                self.chaining_len = 0;
+                return Err(StatusWords::BadChaining);
+            }
+            Some(_) => (),
+            None => self.chaining_len = 0,
+        }
+        self.chaining_header = Some(chain_key);
+
+        let buffer = self.chaining_buffer.as_mut().ok_or(StatusWords::Unknown)?;
+        if self.chaining_len + data.len() > buffer.len() {
+            self.chaining_header = None;
+            self.chaining_len = 0;
+            return Err(StatusWords::BadLen);
+        }
+        buffer[self.chaining_len..self.chaining_len + data.len()].copy_from_slice(data);
+        self.chaining_len += data.len();
+
+        if chaining_bit_set {
+            return Ok(true);
+        }
+        self.chaining_header = None;
+        Ok(false)
+    }
+
+    /// Stages `data` as the response to the current command, to be sent out in
+    /// [`Comm::max_chunk_size`]-sized frames across one or more GET RESPONSE (`INS 0xC0`) APDUs
+    /// if it doesn't fit in a single one.
+    ///
+    /// Call this instead of [`Comm::append`] when the response may exceed the APDU data limit,
+    /// then reply as usual (e.g. with [`Comm::reply_ok`]) to send the first frame, which carries
+    /// the standard `0x61xx`/`0x9000` chaining status word. Replying with any status word other
+    /// than `0x9000` instead drops the staged response and sends that status word as-is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// comm.set_response(&large_response);
+    /// comm.reply_ok();
+    /// ```
+    pub fn set_response(&mut self, data: &'a [u8]) {
+        self.response_buffer = Some(data);
+        self.response_offset = 0;
+    }
+
+    /// Registers `transport` to serve the given `apdu_media` tag: [`Comm::apdu_send`] emits
+    /// responses through it, and [`Comm::decode_event`] polls [`Transport::recv`] on every SEPH
+    /// message it doesn't already turn into an [`Event::Ticker`] or [`Event::Button`], so the
+    /// transport can also receive commands, over a wire protocol beyond the built-in USB
+    /// HID/raw SEPH/CCID/BLE media. Only one custom transport can be registered at a time;
+    /// registering again replaces it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut webusb = WebUsbTransport::new();
+    /// comm.register_transport(APDU_WEBUSB, &mut webusb);
+    /// ```
+    pub fn register_transport(&mut self, apdu_media: u8, transport: &'a mut dyn Transport) {
+        self.custom_transport_media = Some(apdu_media);
+        self.custom_transport = Some(transport);
+    }
+
+    /// Arms a one-shot timer that delivers [`Event::Timeout`] from [`Comm::next_event`]/
+    /// [`Comm::next_event_async`] after `ticks` [`Event::Ticker`]s have elapsed. Returns [`None`]
+    /// if too many timers are already armed.
+    pub fn set_timeout(&mut self, ticks: u64) -> Option<TimerHandle> {
+        self.timers.set_timeout(ticks)
+    }
+
+    /// Like [`Comm::set_timeout`], but re-arms itself every `ticks` ticks instead of firing once.
+    pub fn set_periodic_timeout(&mut self, ticks: u64) -> Option<TimerHandle> {
+        self.timers.set_periodic_timeout(ticks)
+    }
+
+    /// Disarms a timer before it fires; harmless if it already fired or doesn't exist.
+    pub fn cancel_timeout(&mut self, handle: TimerHandle) {
+        self.timers.cancel(handle)
+    }
+
+    /// Copies the next [`Comm::max_chunk_size`] bytes of `response_buffer` into `apdu_buffer`,
+    /// appending `0x61xx` if more chunks remain or `0x9000` once it is fully drained.
+    fn stage_response_chunk(&mut self) {
+        let data = self.response_buffer.unwrap();
+        let remaining = data.len() - self.response_offset;
+        let chunk_len = remaining.min(self.max_chunk_size);
+        self.apdu_buffer[..chunk_len]
+            .copy_from_slice(&data[self.response_offset..self.response_offset + chunk_len]);
+        self.response_offset += chunk_len;
+        self.tx = chunk_len;
+
+        let still_remaining = data.len() - self.response_offset;
+        let sw = if still_remaining > 0 {
+            0x6100 | (still_remaining.min(0xff) as u16)
+        } else {
+            self.response_buffer = None;
+            StatusWords::Ok as u16
+        };
+        self.apdu_buffer[self.tx] = (sw >> 8) as u8;
+        self.apdu_buffer[self.tx + 1] = sw as u8;
+        self.tx += 2;
+    }
+
     /// Send the currently held APDU
     // This is private. Users should call reply to set the satus word and
     // transmit the response.
     fn apdu_send(&mut self) {
+        if self.response_buffer.is_some() {
+            self.stage_response_chunk();
+        }
+
         if !sys_seph::is_status_sent() {
             sys_seph::send_general_status()
         }
@@ -172,27 +414,22 @@ impl Comm {
         }
 
         match unsafe { G_io_app.apdu_state } {
-            APDU_USB_HID => unsafe {
-                ledger_secure_sdk_sys::io_usb_hid_send(
-                    Some(io_usb_send_apdu_data),
-                    self.tx as u16,
-                    self.apdu_buffer.as_mut_ptr(),
-                );
-            },
-            APDU_RAW => {
-                let len = (self.tx as u16).to_be_bytes();
-                sys_seph::seph_send(&[sys_seph::SephTags::RawAPDU as u8, len[0], len[1]]);
-                sys_seph::seph_send(&self.apdu_buffer[..self.tx]);
-            }
+            APDU_USB_HID => transport::HidTransport.send(&self.apdu_buffer[..self.tx]),
+            APDU_RAW => transport::RawTransport.send(&self.apdu_buffer[..self.tx]),
             #[cfg(feature = "ccid")]
-            APDU_USB_CCID => {
-                ccid::send(&self.apdu_buffer[..self.tx]);
-            }
+            APDU_USB_CCID => transport::CcidTransport.send(&self.apdu_buffer[..self.tx]),
             #[cfg(target_os = "nanox")]
-            APDU_BLE => {
-                ble::send(&self.apdu_buffer[..self.tx]);
+            APDU_BLE => transport::BleTransport.send(&self.apdu_buffer[..self.tx]),
+            media => {
+                if self.custom_transport_media == Some(media as u8) {
+                    // Take the transport out so it isn't borrowed through `self` while it
+                    // borrows `self.apdu_buffer` to send.
+                    if let Some(custom) = self.custom_transport.take() {
+                        custom.send(&self.apdu_buffer[..self.tx]);
+                        self.custom_transport = Some(custom);
+                    }
+                }
             }
-            _ => (),
         }
         self.tx = 0;
         self.rx = 0;
@@ -244,27 +481,40 @@ impl Comm {
         T: TryFrom<ApduHeader>,
         Reply: From<<T as TryFrom<ApduHeader>>::Error>,
     {
-        let mut spi_buffer = [0u8; 128];
-
         unsafe {
             G_io_app.apdu_state = APDU_IDLE;
             G_io_app.apdu_media = IO_APDU_MEDIA_NONE;
             G_io_app.apdu_length = 0;
         }
 
-        loop {
-            // Signal end of command stream from SE to MCU
-            // And prepare reception
-            if !sys_seph::is_status_sent() {
-                sys_seph::send_general_status();
-            }
-
-            // Fetch the next message from the MCU
-            let _rx = sys_seph::seph_recv(&mut spi_buffer, 0);
+        executor::block_on(self.next_event_async())
+    }
 
-            if let Some(value) = self.decode_event(&mut spi_buffer) {
-                return value;
-            }
+    /// Async variant of [`Comm::next_event`].
+    ///
+    /// Returns a future that resolves to the next [`Event`] without blocking the calling task:
+    /// each poll drains at most one pending SEPH message, so it can be raced against other
+    /// futures (e.g. a timeout) with a hand-written `select!`-style combinator instead of
+    /// committing to wait for an APDU forever. This is a cooperative *spin* future, not a
+    /// suspending one — see [`executor::block_on`] — so racing it against another spin future
+    /// costs roughly as much as polling both yourself in a loop: nothing here ever suspends the
+    /// task or the CPU, no interrupt wakes it back up, and there is no power saving over a plain
+    /// busy-wait. What it buys is composability — writing the `select!` as ordinary `async fn`
+    /// code — not efficiency. `next_event` itself is just [`executor::block_on`] over this future.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let event = comm.next_event_async::<Instruction>().await;
+    /// ```
+    pub fn next_event_async<T>(&mut self) -> EventFuture<'_, 'a, T>
+    where
+        T: TryFrom<ApduHeader>,
+        Reply: From<<T as TryFrom<ApduHeader>>::Error>,
+    {
+        EventFuture {
+            comm: self,
+            _marker: core::marker::PhantomData,
         }
     }
 
@@ -312,10 +562,33 @@ impl Comm {
             #[cfg(target_os = "nanox")]
             seph::Events::BleReceive => ble::receive(&mut self.apdu_buffer, spi_buffer),
 
-            seph::Events::TickerEvent => return Some(Event::Ticker),
+            seph::Events::TickerEvent => {
+                // Always report the tick itself here: swallowing it in favour of a timer that
+                // also expired on this tick would drop the `Ticker` event, since nothing else
+                // ever produces one. A fired timer is queued in `self.timers` and surfaces as
+                // `Event::Timeout` on the very next poll via `EventFuture::poll`'s/`decode_event`
+                // callers' `pop_pending` drain.
+                self.timers.tick();
+                return Some(Event::Ticker);
+            }
             _ => (),
         }
 
+        // Give a registered custom transport a chance to claim this SEPH message and hand back a
+        // fully reassembled command, mirroring how the built-in media feed `apdu_buffer` via
+        // `seph::handle_capdu_event`/`ble::receive` above.
+        let custom_apdu_len = match &mut self.custom_transport {
+            Some(transport) => transport.recv(&mut self.apdu_buffer, spi_buffer),
+            None => None,
+        };
+        if let (Some(len), Some(media)) = (custom_apdu_len, self.custom_transport_media) {
+            unsafe {
+                G_io_app.apdu_state = media as _;
+                G_io_app.apdu_media = media;
+                G_io_app.apdu_length = len as u16;
+            }
+        }
+
         if unsafe { G_io_app.apdu_state } != APDU_IDLE && unsafe { G_io_app.apdu_length } > 0 {
             self.rx = unsafe { G_io_app.apdu_length as usize };
 
@@ -325,12 +598,74 @@ impl Comm {
                 return None;
             }
 
-            // Check for data length by using `get_data`
-            if let Err(sw) = self.get_data() {
+            // A new APDU block breaks an in-progress GET RESPONSE sequence unless it is itself
+            // the next GET RESPONSE (CLA 0x00, INS 0xC0, the standard ISO 7816-4 class) in that
+            // sequence. Drop a stale staged response here, before any `Comm::reply` below can run,
+            // so a later reply never silently answers with a leftover chunk of the *previous*
+            // response instead of this command's real status word.
+            let header = *self.get_apdu_metadata();
+            let is_get_response = header.cla == 0x00 && header.ins == 0xC0;
+            if self.response_buffer.is_some() && !is_get_response {
+                self.response_buffer = None;
+                self.response_offset = 0;
+            }
+            // The BOLOS default class (`0xB0`/`p1`/`p2` all zero, handled below) is a control
+            // APDU, not application data, so it must never be run through the caller's
+            // reassembly buffer either.
+            let is_bolos_default = header.cla == 0xB0 && header.p1 == 0x00 && header.p2 == 0x00;
+
+            // ISO 7816-4 command chaining: accumulate this block's data field into the
+            // caller-supplied chaining buffer instead of surfacing a command, unless this is the
+            // final (or only) block of the chain. GET RESPONSE and the BOLOS default class are
+            // control APDUs handled below, not part of the application's command stream, so they
+            // bypass the chaining buffer even while one is configured.
+            if self.chaining_buffer.is_some() && !is_get_response && !is_bolos_default {
+                // Stash the data locally: we need to mutate `self.chaining_buffer` right after,
+                // and that can't alias a borrow of `self.apdu_buffer` returned by `get_data`. Only
+                // done on this path so the common (non-chaining) case isn't paying for a 255-byte
+                // stack buffer and a copy on every APDU.
+                let mut data_buf = [0u8; 255];
+                let data_len = match self.get_data() {
+                    Ok(data) => {
+                        let len = data.len();
+                        data_buf[..len].copy_from_slice(data);
+                        len
+                    }
+                    Err(sw) => {
+                        self.reply(sw);
+                        return None;
+                    }
+                };
+
+                let header = *self.get_apdu_metadata();
+                match self.accumulate_chained_block(header, &data_buf[..data_len]) {
+                    Ok(true) => {
+                        // More blocks to come: acknowledge this one and wait for the next.
+                        self.reply_ok();
+                        return None;
+                    }
+                    Ok(false) => {
+                        // Final block: clear the chaining bit so downstream handling sees a
+                        // plain CLA.
+                        self.apdu_buffer[0] &= !0x10;
+                    }
+                    Err(sw) => {
+                        self.reply(sw);
+                        return None;
+                    }
+                }
+            } else if let Err(sw) = self.get_data() {
                 self.reply(sw);
                 return None;
             }
 
+            // GET RESPONSE: hand out the next chunk of a response staged with
+            // `Comm::set_response`, like the BOLOS `0xB0` handling below.
+            if is_get_response && self.response_buffer.is_some() {
+                self.reply_ok();
+                return None;
+            }
+
             // Default BOLOS APDU Handling
             let apdu_header = self.get_apdu_metadata();
             if apdu_header.cla == 0xB0 && apdu_header.p1 == 0x00 && apdu_header.p2 == 0x00 {
@@ -440,6 +775,10 @@ impl Comm {
     /// Set the Status Word of the response to the previous Command event, and
     /// transmit the response.
     ///
+    /// If a response was staged with [`Comm::set_response`] and `sw` is `0x9000`, `sw` is not
+    /// appended: [`Comm::apdu_send`] computes the `0x61xx`/`0x9000` chaining status word for the
+    /// next frame instead. Any other `sw` drops the staged response and is sent as-is.
+    ///
     /// # Arguments
     ///
     /// * `sw` - Status Word to be transmitted after the Data. Can be a
@@ -447,10 +786,20 @@ impl Comm {
     ///   Reply.
     pub fn reply<T: Into<Reply>>(&mut self, reply: T) {
         let sw = reply.into().0;
-        // Append status word
-        self.apdu_buffer[self.tx] = (sw >> 8) as u8;
-        self.apdu_buffer[self.tx + 1] = sw as u8;
-        self.tx += 2;
+        if self.response_buffer.is_some() && sw != StatusWords::Ok as u16 {
+            // A non-OK status word always wins over a response staged with `Comm::set_response`:
+            // without this, an error raised while a response is still being drained (e.g. after
+            // `set_response` but before the caller has drained it via GET RESPONSE) would be
+            // silently swallowed into a success data chunk instead of reaching the host.
+            self.response_buffer = None;
+            self.response_offset = 0;
+        }
+        if self.response_buffer.is_none() {
+            // Append status word
+            self.apdu_buffer[self.tx] = (sw >> 8) as u8;
+            self.apdu_buffer[self.tx + 1] = sw as u8;
+            self.tx += 2;
+        }
         // Transmit the response
         self.apdu_send();
     }
@@ -484,8 +833,9 @@ impl Comm {
                 (0, 5) => Ok(&[]), // Non-conforming zero-data APDU
                 (0, 6) => Err(StatusWords::BadLen),
                 (0, _) => {
+                    // ISO 7816-4 extended-length Lc is two bytes, big-endian.
                     let len =
-                        u16::from_le_bytes([self.apdu_buffer[5], self.apdu_buffer[6]]) as usize;
+                        u16::from_be_bytes([self.apdu_buffer[5], self.apdu_buffer[6]]) as usize;
                     get_data_from_buffer(len, 7)
                 }
                 (len, _) => get_data_from_buffer(len, 5),
@@ -503,16 +853,39 @@ impl Comm {
             self.tx += 1;
         }
     }
+
+    /// Serves a standard "get/set configuration setting" instruction against the [`Config`]
+    /// store, so apps don't have to reinvent this APDU on top of their own NVM layout. `data` is
+    /// `[key_len, key…]` for a GET (the value is appended to the reply via [`Comm::append`]) or
+    /// `[key_len, key…, value…]` for a SET.
+    pub fn handle_config_apdu(&mut self, is_set: bool, data: &[u8]) -> Result<(), StatusWords> {
+        let key_len = *data.first().ok_or(StatusWords::BadLen)? as usize;
+        let key_bytes = data.get(1..1 + key_len).ok_or(StatusWords::BadLen)?;
+        let key = core::str::from_utf8(key_bytes).map_err(|_| StatusWords::BadLen)?;
+
+        if is_set {
+            let value = &data[1 + key_len..];
+            if Config::set(key, value) {
+                Ok(())
+            } else {
+                Err(StatusWords::BadLen)
+            }
+        } else {
+            let value = Config::get(key).ok_or(StatusWords::Unknown)?;
+            self.append(&value);
+            Ok(())
+        }
+    }
 }
 
-impl Index<usize> for Comm {
+impl<'a> Index<usize> for Comm<'a> {
     type Output = u8;
     fn index(&self, idx: usize) -> &Self::Output {
         &self.apdu_buffer[idx]
     }
 }
 
-impl IndexMut<usize> for Comm {
+impl<'a> IndexMut<usize> for Comm<'a> {
     fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
         self.tx = idx.max(self.tx);
         &mut self.apdu_buffer[idx]
@@ -536,4 +909,150 @@ mod test {
         assert_eq!(m.p1, 0);
         assert_eq!(m.p2, 0);
     }
+
+    /// Extended-length Lc is two bytes, big-endian (ISO 7816-4), not little-endian.
+    #[test]
+    fn get_data_extended_length_is_big_endian() {
+        let mut c = Comm::new();
+        c.apdu_buffer[4] = 0x00; // extended-length marker
+        c.apdu_buffer[5] = 0x00; // Lc high byte
+        c.apdu_buffer[6] = 0x82; // Lc low byte: Lc = 130, big-endian
+        for i in 0..130 {
+            c.apdu_buffer[7 + i] = i as u8;
+        }
+        c.rx = 7 + 130;
+
+        let data = c.get_data().expect("valid extended-length APDU");
+        assert_eq!(data.len(), 130);
+        assert_eq!(data[0], 0);
+        assert_eq!(data[129], 129);
+    }
+
+    /// An extended-length block's data, once correctly parsed by `get_data`, fills the chaining
+    /// buffer via `accumulate_chained_block` the same way a short APDU's would.
+    #[test]
+    fn extended_length_block_fills_chaining_buffer() {
+        let mut chain_buf = [0u8; 512];
+        let mut c = Comm::new().with_chaining_buffer(&mut chain_buf);
+
+        // CLA bit 0x10 set: more blocks expected after this one.
+        c.apdu_buffer[0] = 0x10;
+        c.apdu_buffer[4] = 0x00;
+        c.apdu_buffer[5] = 0x00;
+        c.apdu_buffer[6] = 0x82; // Lc = 130, big-endian
+        for i in 0..130 {
+            c.apdu_buffer[7 + i] = i as u8;
+        }
+        c.rx = 7 + 130;
+
+        let mut data_buf = [0u8; 255];
+        let data_len = {
+            let data = c.get_data().expect("valid extended-length APDU");
+            let len = data.len();
+            data_buf[..len].copy_from_slice(data);
+            len
+        };
+        let header = *c.get_apdu_metadata();
+        let more = c
+            .accumulate_chained_block(header, &data_buf[..data_len])
+            .expect("block belongs to a fresh chain");
+        assert!(more, "CLA chaining bit was set, more blocks are expected");
+
+        let chained = c.get_chained_data().unwrap();
+        assert_eq!(chained.len(), 130);
+        assert_eq!(chained[0], 0);
+        assert_eq!(chained[129], 129);
+    }
+
+    /// Any instruction is accepted; exercises `Comm::decode_event`/`Comm::reply` without needing
+    /// a real `TryFrom<ApduHeader>` business type.
+    struct AnyIns;
+
+    impl TryFrom<ApduHeader> for AnyIns {
+        type Error = StatusWords;
+
+        fn try_from(_header: ApduHeader) -> Result<Self, Self::Error> {
+            Ok(AnyIns)
+        }
+    }
+
+    /// A custom [`Transport`] registered on `Comm` both receives a command (via `Transport::recv`
+    /// in `decode_event`) and sends the reply (via `Transport::send` in `apdu_send`), with no real
+    /// SEPH/USB hardware involved.
+    #[test]
+    fn custom_transport_round_trip() {
+        const CUSTOM_MEDIA: u8 = 0xaa;
+
+        let mut mock = crate::transport::MockTransport::new();
+        mock.queue_inbound(&[0x00, 0xa4, 0x00, 0x00]); // 4-byte header, no data
+
+        let mut c = Comm::new();
+        c.register_transport(CUSTOM_MEDIA, &mut mock);
+
+        let mut spi_buffer = [0u8; 128];
+        let event = c.decode_event::<AnyIns>(&mut spi_buffer);
+        assert!(matches!(event, Some(Event::Command(AnyIns))));
+
+        c.reply_ok();
+        assert_eq!(&mock.sent[..mock.sent_len], &[0x90, 0x00]);
+    }
+
+    /// GET RESPONSE and the BOLOS default class are control APDUs: they must bypass a configured
+    /// chaining buffer rather than being fed through `accumulate_chained_block`, which would tear
+    /// down an in-progress application-level chain with a spurious `BadChaining`.
+    #[test]
+    fn control_apdus_bypass_chaining_buffer() {
+        const CUSTOM_MEDIA: u8 = 0xaa;
+
+        let mut chain_buf = [0u8; 512];
+        let mut c = Comm::new().with_chaining_buffer(&mut chain_buf);
+
+        // Start a chain for an ordinary command (CLA chaining bit set, more blocks to come).
+        let chain_header = ApduHeader {
+            cla: 0x10,
+            ins: 0x01,
+            p1: 0x00,
+            p2: 0x00,
+        };
+        let more = c
+            .accumulate_chained_block(chain_header, &[0xaa, 0xbb])
+            .expect("first block of a fresh chain");
+        assert!(more);
+
+        // A BOLOS default-class APDU (get app name/version) arrives mid-chain.
+        let mut mock = crate::transport::MockTransport::new();
+        mock.queue_inbound(&[0xb0, 0x01, 0x00, 0x00]); // 4-byte header, no data
+        c.register_transport(CUSTOM_MEDIA, &mut mock);
+
+        let mut spi_buffer = [0u8; 128];
+        let event = c.decode_event::<AnyIns>(&mut spi_buffer);
+        assert!(event.is_none(), "handled internally by the BOLOS handler");
+
+        // The in-progress chain must be untouched: it wasn't run through accumulation at all.
+        assert_eq!(c.chaining_header, Some([0x00, 0x01, 0x00, 0x00]));
+        assert_eq!(c.get_chained_data().unwrap(), &[0xaa, 0xbb]);
+    }
+
+    /// An error status word raised while a response is staged must reach the host as-is, not be
+    /// overwritten by the staged response's own (success) data chunk.
+    #[test]
+    fn reply_with_error_clears_staged_response() {
+        const CUSTOM_MEDIA: u8 = 0xaa;
+
+        let mut mock = crate::transport::MockTransport::new();
+        mock.queue_inbound(&[0x00, 0xa4, 0x00, 0x00]); // 4-byte header, no data
+
+        let mut c = Comm::new();
+        c.register_transport(CUSTOM_MEDIA, &mut mock);
+
+        let mut spi_buffer = [0u8; 128];
+        let event = c.decode_event::<AnyIns>(&mut spi_buffer);
+        assert!(matches!(event, Some(Event::Command(AnyIns))));
+
+        c.set_response(&[0xde, 0xad, 0xbe, 0xef]);
+        c.reply(StatusWords::BadLen);
+
+        assert!(c.response_buffer.is_none());
+        assert_eq!(&mock.sent[..mock.sent_len], &[0x6e, 0x03]);
+    }
 }
@@ -0,0 +1,224 @@
+/// Maximum number of timers that can be armed at once.
+const MAX_TIMERS: usize = 8;
+
+/// Handle returned by [`TimerQueue::arm`], identifying a timer for as long as it is armed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TimerHandle(usize);
+
+#[derive(Clone, Copy)]
+struct TimerEntry {
+    deadline: u64,
+    handle: TimerHandle,
+    /// `Some(period)` re-arms the timer `period` ticks after it fires; `None` disarms it.
+    period: Option<u64>,
+}
+
+/// Ticker-driven timer/timeout subsystem backing [`crate::io::Comm::set_timeout`] and
+/// [`crate::io::Comm::set_periodic_timeout`].
+///
+/// A monotonic tick counter is advanced once per [`crate::io::Event::Ticker`]; armed timers are
+/// kept in a small fixed-capacity array, sorted by ascending deadline, so [`TimerQueue::tick`]
+/// only has to look at the front of the array and can stop as soon as it sees a timer that isn't
+/// due yet. Every timer due on a given tick is queued in one call to [`TimerQueue::tick`] (there
+/// is no one-per-tick limit); callers must drain [`TimerQueue::pop_pending`] down to [`None`]
+/// after every tick, before waiting for the next SEPH message, both so several timers that expired
+/// together are all reported and because the backing pending-notification array is itself only
+/// [`MAX_TIMERS`] slots deep — a periodic timer left undrained across enough ticks will eventually
+/// find no free slot and that firing is dropped (a debug build fails a `debug_assert!` instead).
+pub struct TimerQueue {
+    ticks: u64,
+    next_id: usize,
+    /// Armed timers, sorted by ascending `deadline`; always contiguous from index 0, with every
+    /// slot from the first `None` onward also `None`.
+    timers: [Option<TimerEntry>; MAX_TIMERS],
+    /// Timers that fired but haven't been reported as an [`crate::io::Event::Timeout`] yet.
+    pending: [Option<TimerHandle>; MAX_TIMERS],
+}
+
+impl TimerQueue {
+    pub const fn new() -> Self {
+        Self {
+            ticks: 0,
+            next_id: 0,
+            timers: [None; MAX_TIMERS],
+            pending: [None; MAX_TIMERS],
+        }
+    }
+
+    /// Arms a one-shot timer that fires `ticks` ticks from now. Returns [`None`] if all
+    /// [`MAX_TIMERS`] slots are already in use.
+    pub fn set_timeout(&mut self, ticks: u64) -> Option<TimerHandle> {
+        self.arm(ticks, None)
+    }
+
+    /// Arms a periodic timer that fires every `ticks` ticks, starting `ticks` ticks from now.
+    /// Returns [`None`] if all [`MAX_TIMERS`] slots are already in use, or if `ticks` is `0`: a
+    /// zero period would re-arm the timer at its own already-passed deadline, so [`TimerQueue::tick`]
+    /// would never stop firing it.
+    pub fn set_periodic_timeout(&mut self, ticks: u64) -> Option<TimerHandle> {
+        if ticks == 0 {
+            return None;
+        }
+        self.arm(ticks, Some(ticks))
+    }
+
+    fn arm(&mut self, ticks: u64, period: Option<u64>) -> Option<TimerHandle> {
+        if self.timers[MAX_TIMERS - 1].is_some() {
+            return None;
+        }
+        let handle = TimerHandle(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.insert(TimerEntry {
+            deadline: self.ticks + ticks,
+            handle,
+            period,
+        });
+        Some(handle)
+    }
+
+    /// Inserts `entry` keeping `self.timers` sorted by ascending deadline. The caller must have
+    /// already checked there is a free slot.
+    fn insert(&mut self, entry: TimerEntry) {
+        let pos = self
+            .timers
+            .iter()
+            .position(|slot| matches!(slot, Some(e) if e.deadline > entry.deadline) || slot.is_none())
+            .expect("arm() checked a free slot exists");
+        let mut i = MAX_TIMERS - 1;
+        while i > pos {
+            self.timers[i] = self.timers[i - 1];
+            i -= 1;
+        }
+        self.timers[pos] = Some(entry);
+    }
+
+    /// Removes the entry at `pos`, sliding the rest of the array left to keep it contiguous.
+    fn remove(&mut self, pos: usize) -> TimerEntry {
+        let entry = self.timers[pos].take().expect("pos names an armed timer");
+        for i in pos..MAX_TIMERS - 1 {
+            self.timers[i] = self.timers[i + 1];
+        }
+        self.timers[MAX_TIMERS - 1] = None;
+        entry
+    }
+
+    /// Disarms a timer before it fires; harmless if it already fired or doesn't exist.
+    pub fn cancel(&mut self, handle: TimerHandle) {
+        if let Some(pos) = self
+            .timers
+            .iter()
+            .position(|slot| matches!(slot, Some(e) if e.handle == handle))
+        {
+            self.remove(pos);
+        }
+    }
+
+    /// Advances the tick counter by one and queues every timer whose deadline has now passed
+    /// (not just the first one), re-arming periodic ones. Call [`TimerQueue::pop_pending`] in a
+    /// loop until it returns [`None`] to drain the result.
+    pub fn tick(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+        // Sorted by deadline, so the first timer that isn't due yet means none after it are
+        // either.
+        while matches!(self.timers[0], Some(entry) if entry.deadline <= self.ticks) {
+            let entry = self.remove(0);
+            match self.pending.iter_mut().find(|p| p.is_none()) {
+                Some(pending) => *pending = Some(entry.handle),
+                // Only reachable if `pop_pending` wasn't drained for `MAX_TIMERS` firings in a
+                // row; see `TimerQueue`'s docs. Release builds drop this notification, so a slow
+                // caller doesn't take down the whole app over a timer; debug builds panic here.
+                None => debug_assert!(false, "TimerQueue::pending is full, dropping a firing"),
+            }
+            if let Some(period) = entry.period {
+                self.insert(TimerEntry {
+                    deadline: entry.deadline + period,
+                    ..entry
+                });
+            }
+        }
+    }
+
+    /// Pops one timer that fired and hasn't been reported yet, if any.
+    pub fn pop_pending(&mut self) -> Option<TimerHandle> {
+        self.pending.iter_mut().find_map(|p| p.take())
+    }
+}
+
+impl Default for TimerQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assert_eq_err as assert_eq;
+    use crate::testing::TestType;
+    use testmacro::test_item as test;
+
+    #[test]
+    fn one_shot_fires_once() {
+        let mut q = TimerQueue::new();
+        let handle = q.set_timeout(3).unwrap();
+
+        q.tick();
+        q.tick();
+        assert_eq!(q.pop_pending(), None);
+
+        q.tick();
+        assert_eq!(q.pop_pending(), Some(handle));
+        assert_eq!(q.pop_pending(), None);
+
+        // Disarmed after firing: further ticks report nothing more.
+        q.tick();
+        assert_eq!(q.pop_pending(), None);
+    }
+
+    #[test]
+    fn periodic_fires_every_period() {
+        let mut q = TimerQueue::new();
+        let handle = q.set_periodic_timeout(2).unwrap();
+
+        q.tick();
+        assert_eq!(q.pop_pending(), None);
+        q.tick();
+        assert_eq!(q.pop_pending(), Some(handle));
+        q.tick();
+        assert_eq!(q.pop_pending(), None);
+        q.tick();
+        assert_eq!(q.pop_pending(), Some(handle));
+    }
+
+    #[test]
+    fn fires_in_deadline_order_even_if_armed_out_of_order() {
+        let mut q = TimerQueue::new();
+        let later = q.set_timeout(5).unwrap();
+        let sooner = q.set_timeout(1).unwrap();
+
+        q.tick();
+        assert_eq!(q.pop_pending(), Some(sooner));
+        assert_eq!(q.pop_pending(), None);
+
+        for _ in 0..4 {
+            q.tick();
+        }
+        assert_eq!(q.pop_pending(), Some(later));
+    }
+
+    #[test]
+    fn zero_period_is_rejected() {
+        let mut q = TimerQueue::new();
+        assert_eq!(q.set_periodic_timeout(0), None);
+    }
+
+    #[test]
+    fn cancel_before_deadline_suppresses_it() {
+        let mut q = TimerQueue::new();
+        let handle = q.set_timeout(1).unwrap();
+        q.cancel(handle);
+
+        q.tick();
+        assert_eq!(q.pop_pending(), None);
+    }
+}
@@ -0,0 +1,118 @@
+use ledger_secure_sdk_sys::seph as sys_seph;
+
+#[cfg(target_os = "nanox")]
+use crate::ble;
+#[cfg(feature = "ccid")]
+use crate::ccid;
+
+/// A wire protocol [`crate::io::Comm`] can send and receive an APDU over.
+///
+/// Built-in media (USB HID, the raw SEPH protocol, CCID, BLE) are matched directly on
+/// `G_io_app.apdu_state` inside [`crate::io::Comm`]; this trait is for an application that needs to add
+/// its own, e.g. a WebUSB-style bulk interface, without patching `Comm` itself. Register one with
+/// [`crate::io::Comm::register_transport`].
+pub trait Transport {
+    /// Send one complete APDU response.
+    fn send(&mut self, apdu: &[u8]);
+
+    /// Dispatches one just-received SEPH message addressed to this transport: if it completes a
+    /// command APDU, copy it into `apdu_buffer` and return its length, mirroring what
+    /// `seph::handle_capdu_event`/`ble::receive` do for the built-in media. Returns `None` for a
+    /// message that isn't this transport's concern (the default, correct for `send`-only media so
+    /// they don't have to restate it) or one that doesn't complete a command yet.
+    fn recv(&mut self, apdu_buffer: &mut [u8], spi_buffer: &[u8; 128]) -> Option<usize> {
+        let _ = (apdu_buffer, spi_buffer);
+        None
+    }
+}
+
+/// [`Transport`] for the USB HID media (`APDU_USB_HID`).
+pub struct HidTransport;
+
+impl Transport for HidTransport {
+    fn send(&mut self, apdu: &[u8]) {
+        unsafe {
+            ledger_secure_sdk_sys::io_usb_hid_send(
+                Some(ledger_secure_sdk_sys::io_usb_send_apdu_data),
+                apdu.len() as u16,
+                apdu.as_ptr() as *mut u8,
+            );
+        }
+    }
+}
+
+/// [`Transport`] for the raw SEPH protocol (`APDU_RAW`), used by e.g. Speculos.
+pub struct RawTransport;
+
+impl Transport for RawTransport {
+    fn send(&mut self, apdu: &[u8]) {
+        let len = (apdu.len() as u16).to_be_bytes();
+        sys_seph::seph_send(&[sys_seph::SephTags::RawAPDU as u8, len[0], len[1]]);
+        sys_seph::seph_send(apdu);
+    }
+}
+
+/// [`Transport`] for USB CCID (`APDU_USB_CCID`).
+#[cfg(feature = "ccid")]
+pub struct CcidTransport;
+
+#[cfg(feature = "ccid")]
+impl Transport for CcidTransport {
+    fn send(&mut self, apdu: &[u8]) {
+        ccid::send(apdu);
+    }
+}
+
+/// [`Transport`] for BLE (`APDU_BLE`), Nano X only.
+#[cfg(target_os = "nanox")]
+pub struct BleTransport;
+
+#[cfg(target_os = "nanox")]
+impl Transport for BleTransport {
+    fn send(&mut self, apdu: &[u8]) {
+        ble::send(apdu);
+    }
+}
+
+/// In-memory [`Transport`] for tests: records every [`Transport::send`], and replays one scripted
+/// inbound APDU from [`Transport::recv`]. Lets `Comm::decode_event`/`Comm::apdu_send` round-trips
+/// be exercised without any real SEPH/USB hardware, which is impossible for the built-in media
+/// since they call straight into `sys_seph`.
+#[cfg(test)]
+pub struct MockTransport {
+    pub sent: [u8; 260],
+    pub sent_len: usize,
+    inbound: Option<([u8; 260], usize)>,
+}
+
+#[cfg(test)]
+impl MockTransport {
+    pub fn new() -> Self {
+        Self {
+            sent: [0u8; 260],
+            sent_len: 0,
+            inbound: None,
+        }
+    }
+
+    /// Scripts `apdu` to be handed back the next time [`Transport::recv`] is polled.
+    pub fn queue_inbound(&mut self, apdu: &[u8]) {
+        let mut buf = [0u8; 260];
+        buf[..apdu.len()].copy_from_slice(apdu);
+        self.inbound = Some((buf, apdu.len()));
+    }
+}
+
+#[cfg(test)]
+impl Transport for MockTransport {
+    fn send(&mut self, apdu: &[u8]) {
+        self.sent[..apdu.len()].copy_from_slice(apdu);
+        self.sent_len = apdu.len();
+    }
+
+    fn recv(&mut self, apdu_buffer: &mut [u8], _spi_buffer: &[u8; 128]) -> Option<usize> {
+        let (buf, len) = self.inbound.take()?;
+        apdu_buffer[..len].copy_from_slice(&buf[..len]);
+        Some(len)
+    }
+}